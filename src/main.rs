@@ -1,16 +1,30 @@
-use std::time::Duration;
+pub(crate) mod ops;
+mod quadtree;
+mod soft_body;
+mod spatial_hash;
+
+use std::collections::HashMap;
 
 use bevy::prelude::Entity;
-use bevy::time::common_conditions::on_timer;
 use bevy::window::PrimaryWindow;
 use bevy::{log::LogPlugin, prelude::*};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_inspector_egui::{prelude::ReflectInspectorOptions, InspectorOptions};
-use rand::random;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use quadtree::Quadtree;
+use soft_body::SoftBody;
+use spatial_hash::SpatialHash;
 
 pub const PHYSICS_ITERS: u8 = 4;
 pub const PHYSICS_DT: f32 = 1.0 / 60.0;
 pub const PHYSICS_ITER_DT: f32 = PHYSICS_DT / (PHYSICS_ITERS as f32);
+/// Roughly twice the largest `Point::radius` in use, so a 3x3 cell block
+/// around a point is guaranteed to cover its full interaction radius.
+pub const SPATIAL_HASH_CELL_SIZE: f32 = 20.0;
+/// Fixed seed so replays of the same scenario draw the same initial velocities.
+pub const PHYSICS_SEED: u64 = 0x5EED_5EED;
 
 fn main() {
     App::new()
@@ -22,9 +36,20 @@ fn main() {
         .add_plugins(WorldInspectorPlugin::new())
         .register_type::<Point>()
         .register_type::<Constraint>()
+        .register_type::<Boid>()
+        .register_type::<FlockingConfig>()
+        .register_type::<CollisionConfig>()
+        .register_type::<PreviousPosition>()
+        .register_type::<Tunneling>()
+        .register_type::<GravityConfig>()
+        .insert_resource(FlockingConfig::default())
+        .insert_resource(CollisionConfig::default())
+        .insert_resource(SpatialHash::new(SPATIAL_HASH_CELL_SIZE))
+        .insert_resource(GravityConfig::default())
+        .insert_resource(PhysicsRng::default())
+        .insert_resource(Time::<Fixed>::from_seconds(PHYSICS_DT as f64))
         .add_systems(Startup, (spawn_entities).chain())
-        .add_systems(Update, (apply_velocities, compute_boundaries, compute_constraints, update_positions).chain()
-            .run_if(on_timer(Duration::from_millis((1000.0 * PHYSICS_DT) as u64))))
+        .add_systems(FixedUpdate, (apply_velocities, compute_boundaries, settle_tunneling, build_spatial_hash, compute_flocking, apply_gravity, compute_constraints, rebuild_spatial_hash_for_collisions, resolve_collisions, update_positions).chain())
         .add_systems(Update, (debug_points, debug_constraints))
         .run();
 }
@@ -54,16 +79,106 @@ pub struct Constraint {
     pub strength: f32,
 }
 
-pub fn make_point(position: Vec2) -> impl Bundle {
-    Point {
-        position,
-        velocity: Vec2::new(random::<f32>() - 0.5, random::<f32>() - 0.5).normalize(),
-        friction: 1.0,
-        radius: 10.0,
-        mass: 10.0,
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Boid;
+
+#[derive(Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct FlockingConfig {
+    pub perception_radius: f32,
+    pub separation_radius: f32,
+    pub max_force: f32,
+    pub max_speed: f32,
+    pub separation_weight: f32,
+    pub alignment_weight: f32,
+    pub cohesion_weight: f32,
+}
+
+impl Default for FlockingConfig {
+    fn default() -> Self {
+        Self {
+            perception_radius: 80.0,
+            separation_radius: 30.0,
+            max_force: 0.5,
+            max_speed: 4.0,
+            separation_weight: 1.5,
+            alignment_weight: 1.0,
+            cohesion_weight: 1.0,
+        }
     }
 }
 
+#[derive(Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct CollisionConfig {
+    pub restitution: f32,
+}
+
+impl Default for CollisionConfig {
+    fn default() -> Self {
+        Self { restitution: 0.0 }
+    }
+}
+
+#[derive(Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct GravityConfig {
+    pub enabled: bool,
+    pub g: f32,
+    pub theta: f32,
+    pub epsilon: f32,
+}
+
+impl Default for GravityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            g: 50.0,
+            theta: 0.5,
+            epsilon: 5.0,
+        }
+    }
+}
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct PreviousPosition(pub Vec2);
+
+/// Marks a point that just had a fast boundary crossing resolved discontinuously.
+/// While it has `frames` remaining, `settle_tunneling` nudges its position a
+/// small step along `dir` each tick, on top of its already-reflected velocity.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Tunneling {
+    pub frames: u8,
+    pub dir: Vec2,
+}
+
+/// Seeded PRNG for anything that feeds into the physics step, so simulations
+/// seeded the same way replay identically regardless of machine or run order.
+#[derive(Resource)]
+pub struct PhysicsRng(pub StdRng);
+
+impl Default for PhysicsRng {
+    fn default() -> Self {
+        Self(StdRng::seed_from_u64(PHYSICS_SEED))
+    }
+}
+
+pub fn make_point(position: Vec2, rng: &mut PhysicsRng) -> impl Bundle {
+    (
+        Point {
+            position,
+            velocity: ops::normalize(Vec2::new(rng.0.gen::<f32>() - 0.5, rng.0.gen::<f32>() - 0.5)),
+            friction: 1.0,
+            radius: 10.0,
+            mass: 10.0,
+        },
+        PreviousPosition(position),
+    )
+}
+
 pub fn make_constraint(point_a: Entity, point_b: Entity, length: f32) -> impl Bundle {
     Constraint {
         point_a,
@@ -73,22 +188,12 @@ pub fn make_constraint(point_a: Entity, point_b: Entity, length: f32) -> impl Bu
     }
 }
 
-pub fn spawn_entities(mut commands: Commands) {
+pub fn spawn_entities(mut commands: Commands, mut physics_rng: ResMut<PhysicsRng>) {
     commands.spawn(Camera2dBundle::default());
-    let a = commands.spawn(make_point(Vec2::new(0.0, 0.0))).id();
-    let b = commands.spawn(make_point(Vec2::new(100.0, 0.0))).id();
-    let c = commands.spawn(make_point(Vec2::new(100.0, 100.0))).id();
-    let d = commands.spawn(make_point(Vec2::new(0.0, 100.0))).id();
-    commands.spawn(make_constraint(a, b, 100.0));
-    commands.spawn(make_constraint(b, c, 100.0));
-    commands.spawn(make_constraint(c, d, 100.0));
-    commands.spawn(make_constraint(d, a, 100.0));
-    commands.spawn(make_constraint(a, c, 100.0 * 2.0_f32.sqrt()));
-    commands.spawn(make_constraint(b, d, 100.0 * 2.0_f32.sqrt()));
-    let e = commands.spawn(make_point(Vec2::new(0.0, -100.0))).id();
-    commands.spawn(make_constraint(a, e, 100.0));
-
-
+    let grid = SoftBody::grid(&mut commands, &mut physics_rng, 2, 2, 100.0);
+    let tail = commands.spawn(make_point(Vec2::new(0.0, -100.0), &mut physics_rng)).id();
+    commands.spawn(make_constraint(grid[0], tail, 100.0));
+    SoftBody::ring(&mut commands, &mut physics_rng, 8, 80.0);
 }
 
 macro_rules! constraint_points {
@@ -112,33 +217,60 @@ pub fn apply_velocities(mut point_query: Query<&mut Point>) {
 }
 
 pub fn compute_boundaries(
-    mut point_query: Query<&mut Point>,
+    mut commands: Commands,
+    mut point_query: Query<(Entity, &mut Point, &PreviousPosition)>,
     window_query: Query<&Window, With<PrimaryWindow>>,
 ) {
     let window = window_query.get_single();
     if let Ok(window) = window {
-        for mut point in point_query.iter_mut() {
+        for (entity, mut point, previous_position) in point_query.iter_mut() {
             let width2 = window.width() / 2.0 - point.radius;
             let height2 = window.height() / 2.0 - point.radius;
             let r = -1.0;
             let mut pos_effect = point.position;
             let mut bounce_x = false;
             let mut bounce_y = false;
-            if point.position.x < -width2 {
-                pos_effect.x = -width2;
-                bounce_x = true;
-            }
-            if point.position.y < -height2 {
-                pos_effect.y = -height2;
-                bounce_y = true;
-            }
-            if point.position.x > width2 {
-                pos_effect.x = width2;
-                bounce_x = true;
+            let mut swept_hit = false;
+
+            if ops::length(point.velocity) > point.radius {
+                if let Some((hit_position, hit_x, hit_y)) =
+                    sweep_boundary(previous_position.0, point.future_position(), width2, height2)
+                {
+                    pos_effect = hit_position;
+                    bounce_x = hit_x;
+                    bounce_y = hit_y;
+                    swept_hit = true;
+                    commands.entity(entity).insert(Tunneling {
+                        frames: 3,
+                        dir: Vec2::new(
+                            if hit_x { -point.velocity.x.signum() } else { 0.0 },
+                            if hit_y { -point.velocity.y.signum() } else { 0.0 },
+                        ),
+                    });
+                }
             }
-            if point.position.y > height2 {
-                pos_effect.y = height2;
-                bounce_y = true;
+
+            // The sweep already resolved the earliest wall crossing this tick;
+            // only fall back to the discrete check when it found none, or it'll
+            // clobber the swept hit with post-`apply_velocities` positions that
+            // may already be past more than one plane.
+            if !swept_hit {
+                if point.position.x < -width2 {
+                    pos_effect.x = -width2;
+                    bounce_x = true;
+                }
+                if point.position.y < -height2 {
+                    pos_effect.y = -height2;
+                    bounce_y = true;
+                }
+                if point.position.x > width2 {
+                    pos_effect.x = width2;
+                    bounce_x = true;
+                }
+                if point.position.y > height2 {
+                    pos_effect.y = height2;
+                    bounce_y = true;
+                }
             }
             if bounce_x || bounce_y {
                 point.position = pos_effect;
@@ -153,6 +285,158 @@ pub fn compute_boundaries(
     }
 }
 
+/// Intersects the swept segment `start..end` against the four planes of a
+/// `width2 x height2` half-extent box and returns the earliest crossing as
+/// `(position, hit_vertical_wall, hit_horizontal_wall)`.
+fn sweep_boundary(start: Vec2, end: Vec2, width2: f32, height2: f32) -> Option<(Vec2, bool, bool)> {
+    let delta = end - start;
+    let mut earliest: Option<(f32, bool, bool)> = None;
+
+    if delta.x.abs() > f32::EPSILON {
+        for &plane_x in &[-width2, width2] {
+            let t = (plane_x - start.x) / delta.x;
+            let y = start.y + delta.y * t;
+            if (0.0..=1.0).contains(&t)
+                && delta.x * plane_x > 0.0
+                && y >= -height2
+                && y <= height2
+                && earliest.is_none_or(|(best, ..)| t < best)
+            {
+                earliest = Some((t, true, false));
+            }
+        }
+    }
+    if delta.y.abs() > f32::EPSILON {
+        for &plane_y in &[-height2, height2] {
+            let t = (plane_y - start.y) / delta.y;
+            let x = start.x + delta.x * t;
+            if (0.0..=1.0).contains(&t)
+                && delta.y * plane_y > 0.0
+                && x >= -width2
+                && x <= width2
+                && earliest.is_none_or(|(best, ..)| t < best)
+            {
+                earliest = Some((t, false, true));
+            }
+        }
+    }
+
+    earliest.map(|(t, hit_x, hit_y)| (start + delta * t, hit_x, hit_y))
+}
+
+pub fn settle_tunneling(mut commands: Commands, mut point_query: Query<(Entity, &mut Point, &mut Tunneling)>) {
+    for (entity, mut point, mut tunneling) in point_query.iter_mut() {
+        let step = tunneling.dir * point.radius * 0.1;
+        point.position += step;
+        tunneling.frames -= 1;
+        if tunneling.frames == 0 {
+            commands.entity(entity).remove::<Tunneling>();
+        }
+    }
+}
+
+fn fill_spatial_hash(
+    spatial_hash: &mut SpatialHash,
+    point_query: &Query<(Entity, &Point)>,
+    position_of: impl Fn(&Point) -> Vec2,
+) {
+    spatial_hash.clear();
+    for (entity, point) in point_query.iter() {
+        spatial_hash.insert(entity, position_of(point));
+    }
+}
+
+pub fn build_spatial_hash(mut spatial_hash: ResMut<SpatialHash>, point_query: Query<(Entity, &Point)>) {
+    fill_spatial_hash(&mut spatial_hash, &point_query, |point| point.position);
+}
+
+/// Rebuilds the spatial hash from each point's `future_position()` rather
+/// than its current `position`. `compute_flocking`/`apply_gravity`/
+/// `compute_constraints` all adjust velocity after `build_spatial_hash` ran,
+/// so by the time `resolve_collisions` queries by `future_position()` a fast
+/// point may have moved out of the cell it was indexed under; rebuilding
+/// here keeps the broadphase in sync with the positions it's actually
+/// queried against.
+pub fn rebuild_spatial_hash_for_collisions(
+    mut spatial_hash: ResMut<SpatialHash>,
+    point_query: Query<(Entity, &Point)>,
+) {
+    fill_spatial_hash(&mut spatial_hash, &point_query, |point| point.future_position());
+}
+
+pub fn compute_flocking(
+    config: Res<FlockingConfig>,
+    spatial_hash: Res<SpatialHash>,
+    mut point_query: Query<(Entity, &mut Point), With<Boid>>,
+) {
+    let snapshot: HashMap<Entity, (Vec2, Vec2)> = point_query
+        .iter()
+        .map(|(entity, point)| (entity, (point.position, point.velocity)))
+        .collect();
+
+    for (entity, mut point) in point_query.iter_mut() {
+        let mut separation = Vec2::ZERO;
+        let mut alignment = Vec2::ZERO;
+        let mut cohesion = Vec2::ZERO;
+        let mut count = 0;
+
+        for candidate in spatial_hash.query_neighbors(point.position, config.perception_radius) {
+            if candidate == entity {
+                continue;
+            }
+            let Some(&(other_position, other_velocity)) = snapshot.get(&candidate) else {
+                continue;
+            };
+            let delta = point.position - other_position;
+            let distance = ops::length(delta);
+            if distance == 0.0 || distance > config.perception_radius {
+                continue;
+            }
+
+            if distance < config.separation_radius {
+                separation += delta / distance;
+            }
+            alignment += other_velocity;
+            cohesion += other_position;
+            count += 1;
+        }
+
+        if count == 0 {
+            continue;
+        }
+
+        alignment = alignment / count as f32 - point.velocity;
+        cohesion = cohesion / count as f32 - point.position;
+
+        let acceleration = ops::clamp_length_max(
+            separation * config.separation_weight
+                + alignment * config.alignment_weight
+                + cohesion * config.cohesion_weight,
+            config.max_force,
+        );
+
+        point.velocity = ops::clamp_length_max(point.velocity + acceleration, config.max_speed);
+    }
+}
+
+pub fn apply_gravity(config: Res<GravityConfig>, mut point_query: Query<(Entity, &mut Point)>) {
+    if !config.enabled {
+        return;
+    }
+
+    let bodies: Vec<(Entity, Vec2, f32)> = point_query
+        .iter()
+        .map(|(entity, point)| (entity, point.position, point.mass))
+        .collect();
+    let quadtree = Quadtree::build(&bodies);
+
+    for (entity, mut point) in point_query.iter_mut() {
+        let force = quadtree.force_on(entity, point.position, config.theta, config.g, config.epsilon);
+        let mass = point.mass;
+        point.velocity += force / mass;
+    }
+}
+
 pub fn compute_constraints(
     mut point_query: Query<&mut Point>,
     constraint_query: Query<&Constraint>,
@@ -163,7 +447,7 @@ pub fn compute_constraints(
                 let pos_a = point_a.future_position();
                 let pos_b = point_b.future_position();
                 let delta = pos_b - pos_a;
-                let distance = pos_a.distance(pos_b);
+                let distance = ops::distance(pos_a, pos_b);
                 let diff = (constraint.length - distance) / distance * constraint.strength * 2.0;
                 let offset = delta * diff * 0.5;
                 let effect_a = (1.0 / point_a.mass) / ((1.0 / point_a.mass) + (1.0 / point_b.mass));
@@ -176,8 +460,73 @@ pub fn compute_constraints(
     }
 }
 
-pub fn update_positions(mut point_query: Query<&mut Point>) {
-    for mut point in point_query.iter_mut() {
+pub fn resolve_collisions(
+    mut point_query: Query<(Entity, &mut Point)>,
+    spatial_hash: Res<SpatialHash>,
+    config: Res<CollisionConfig>,
+) {
+    let snapshot: Vec<(Entity, Vec2, f32)> = point_query
+        .iter()
+        .map(|(entity, point)| (entity, point.future_position(), point.radius))
+        .collect();
+
+    // A pair can only collide within own_radius + the other point's radius, and
+    // the other point's radius isn't known until the hash is queried. Querying
+    // with own_radius + max_radius (rather than own_radius + own_radius) is the
+    // only way a single-sided query range is guaranteed to cover every partner,
+    // no matter which side of the pair has the smaller id and so does the querying.
+    let max_radius = snapshot
+        .iter()
+        .map(|(.., radius)| *radius)
+        .fold(0.0_f32, f32::max);
+
+    for (entity, position, radius) in snapshot {
+        for candidate in spatial_hash.query_neighbors(position, radius + max_radius) {
+            // Entity ordering dedupes each unordered pair to a single resolution.
+            if candidate <= entity {
+                continue;
+            }
+            let Ok([(_, mut point_a), (_, mut point_b)]) = point_query.get_many_mut([entity, candidate]) else {
+                continue;
+            };
+
+            let pos_a = point_a.future_position();
+            let pos_b = point_b.future_position();
+            let min_distance = point_a.radius + point_b.radius;
+            let delta = pos_a - pos_b;
+            let distance = ops::length(delta);
+            if distance >= min_distance {
+                continue;
+            }
+
+            let normal = if distance > 0.0 {
+                delta / distance
+            } else {
+                Vec2::new(1.0, 0.0)
+            };
+            let penetration = min_distance - distance;
+            let effect_a = (1.0 / point_a.mass) / ((1.0 / point_a.mass) + (1.0 / point_b.mass));
+            let effect_b = 1.0 - effect_a;
+
+            point_a.velocity += normal * penetration * effect_a;
+            point_b.velocity -= normal * penetration * effect_b;
+
+            if config.restitution > 0.0 {
+                let relative_velocity = point_a.velocity - point_b.velocity;
+                let speed_along_normal = relative_velocity.dot(normal);
+                if speed_along_normal < 0.0 {
+                    let impulse = -(1.0 + config.restitution) * speed_along_normal;
+                    point_a.velocity += normal * impulse * effect_a;
+                    point_b.velocity -= normal * impulse * effect_b;
+                }
+            }
+        }
+    }
+}
+
+pub fn update_positions(mut point_query: Query<(&mut Point, &mut PreviousPosition)>) {
+    for (mut point, mut previous_position) in point_query.iter_mut() {
+        previous_position.0 = point.position;
         let velocity = point.velocity * point.friction;
         point.position += velocity;
         point.velocity = velocity;
@@ -0,0 +1,40 @@
+//! Deterministic replacements for the handful of float operations the
+//! physics step relies on. Plain `f32::sqrt`/`Vec2::distance`/`Vec2::normalize`
+//! may round differently across targets; routing them through `libm` instead
+//! keeps a recorded simulation replaying bit-identically everywhere.
+use bevy::prelude::Vec2;
+
+pub fn sqrt(x: f32) -> f32 {
+    libm::sqrtf(x)
+}
+
+pub fn length(v: Vec2) -> f32 {
+    sqrt(v.x * v.x + v.y * v.y)
+}
+
+pub fn distance(a: Vec2, b: Vec2) -> f32 {
+    length(a - b)
+}
+
+pub fn normalize(v: Vec2) -> Vec2 {
+    v / length(v)
+}
+
+/// Equivalent to `Vec2::clamp_length_max`, routed through `length` above so
+/// the clamp itself stays bit-identical across targets.
+pub fn clamp_length_max(v: Vec2, max: f32) -> Vec2 {
+    let len = length(v);
+    if len > max && len > 0.0 {
+        v * (max / len)
+    } else {
+        v
+    }
+}
+
+pub fn sin(x: f32) -> f32 {
+    libm::sinf(x)
+}
+
+pub fn cos(x: f32) -> f32 {
+    libm::cosf(x)
+}
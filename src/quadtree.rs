@@ -0,0 +1,221 @@
+use bevy::prelude::*;
+
+use crate::ops;
+
+enum NodeContent {
+    Empty,
+    Leaf {
+        /// Usually a single entity; holds more than one only when several
+        /// bodies land in the same leaf at `Node::MAX_DEPTH`, so `force_on`'s
+        /// self-exclusion can still match every one of them.
+        entities: Vec<Entity>,
+        position: Vec2,
+        mass: f32,
+    },
+    Internal {
+        children: Box<[Node; 4]>,
+    },
+}
+
+struct Node {
+    center: Vec2,
+    half_size: f32,
+    mass: f32,
+    center_of_mass: Vec2,
+    content: NodeContent,
+}
+
+impl Node {
+    fn new(center: Vec2, half_size: f32) -> Self {
+        Self {
+            center,
+            half_size,
+            mass: 0.0,
+            center_of_mass: Vec2::ZERO,
+            content: NodeContent::Empty,
+        }
+    }
+
+    fn quadrant_of(&self, position: Vec2) -> usize {
+        match (position.x >= self.center.x, position.y >= self.center.y) {
+            (false, false) => 0,
+            (true, false) => 1,
+            (false, true) => 2,
+            (true, true) => 3,
+        }
+    }
+
+    fn child_center(&self, index: usize) -> Vec2 {
+        let offset = self.half_size / 2.0;
+        let dx = if index == 1 || index == 3 { offset } else { -offset };
+        let dy = if index == 2 || index == 3 { offset } else { -offset };
+        self.center + Vec2::new(dx, dy)
+    }
+
+    fn subdivide(&mut self) {
+        let half = self.half_size / 2.0;
+        self.content = NodeContent::Internal {
+            children: Box::new([
+                Node::new(self.child_center(0), half),
+                Node::new(self.child_center(1), half),
+                Node::new(self.child_center(2), half),
+                Node::new(self.child_center(3), half),
+            ]),
+        };
+    }
+
+    fn insert(&mut self, entity: Entity, position: Vec2, mass: f32) {
+        self.insert_at_depth(entity, position, mass, 0);
+    }
+
+    /// Subdivision halves `half_size` every level, so two bodies at (or very
+    /// near) the same position can route into the same quadrant forever —
+    /// `quadrant_of` never disagrees with itself. `MAX_DEPTH` bounds that
+    /// recursion: once reached, further bodies are folded into this node's
+    /// mass/center of mass as a single combined leaf instead of recursing.
+    const MAX_DEPTH: u32 = 32;
+
+    fn insert_at_depth(&mut self, entity: Entity, position: Vec2, mass: f32, depth: u32) {
+        if mass <= 0.0 {
+            return;
+        }
+
+        if depth < Self::MAX_DEPTH && matches!(self.content, NodeContent::Leaf { .. }) {
+            let existing = std::mem::replace(&mut self.content, NodeContent::Empty);
+            if let NodeContent::Leaf {
+                entities: existing_entities,
+                position: existing_position,
+                mass: existing_mass,
+            } = existing
+            {
+                self.subdivide();
+                let index = self.quadrant_of(existing_position);
+                // A leaf below MAX_DEPTH always holds exactly one entity (a
+                // second insert here would have subdivided it already), so
+                // the mass is carried through whole rather than split.
+                let per_entity_mass = existing_mass / existing_entities.len() as f32;
+                if let NodeContent::Internal { children } = &mut self.content {
+                    for existing_entity in existing_entities {
+                        children[index].insert_at_depth(existing_entity, existing_position, per_entity_mass, depth + 1);
+                    }
+                }
+            }
+        }
+
+        let index = self.quadrant_of(position);
+        match &mut self.content {
+            NodeContent::Empty => {
+                self.content = NodeContent::Leaf {
+                    entities: vec![entity],
+                    position,
+                    mass,
+                };
+            }
+            NodeContent::Internal { children } if depth < Self::MAX_DEPTH => {
+                children[index].insert_at_depth(entity, position, mass, depth + 1);
+            }
+            NodeContent::Leaf { entities, .. } => {
+                // Max depth reached: track every coincident entity so
+                // force_on's self-exclusion still matches each of them,
+                // instead of only the first one inserted here.
+                entities.push(entity);
+            }
+            NodeContent::Internal { .. } => {
+                // Max depth reached on an already-subdivided node: merge into
+                // this node's aggregate below rather than subdividing further.
+            }
+        }
+
+        let total_mass = self.mass + mass;
+        self.center_of_mass = (self.center_of_mass * self.mass + position * mass) / total_mass;
+        self.mass = total_mass;
+    }
+
+    fn force_on(&self, entity: Entity, position: Vec2, theta: f32, g: f32, epsilon: f32) -> Vec2 {
+        if self.mass <= 0.0 {
+            return Vec2::ZERO;
+        }
+
+        match &self.content {
+            NodeContent::Empty => Vec2::ZERO,
+            NodeContent::Leaf { entities, .. } if entities.contains(&entity) => Vec2::ZERO,
+            NodeContent::Leaf { .. } => self.point_mass_force(position, g, epsilon),
+            NodeContent::Internal { children } => {
+                let distance = ops::length(self.center_of_mass - position);
+                if self.half_size * 2.0 / distance < theta {
+                    self.point_mass_force(position, g, epsilon)
+                } else {
+                    children
+                        .iter()
+                        .map(|child| child.force_on(entity, position, theta, g, epsilon))
+                        .sum()
+                }
+            }
+        }
+    }
+
+    fn point_mass_force(&self, position: Vec2, g: f32, epsilon: f32) -> Vec2 {
+        let delta = self.center_of_mass - position;
+        let distance_sq = delta.length_squared() + epsilon * epsilon;
+        let distance = ops::sqrt(distance_sq);
+        delta / distance * (g * self.mass / distance_sq)
+    }
+}
+
+/// A Barnes-Hut quadtree over 2D point masses, used to approximate n-body
+/// gravitational attraction in `O(n log n)` instead of `O(n^2)`.
+pub struct Quadtree {
+    root: Node,
+}
+
+impl Quadtree {
+    /// Builds a quadtree over `bodies` (entity, position, mass), bounded by a
+    /// square large enough to contain every position.
+    pub fn build(bodies: &[(Entity, Vec2, f32)]) -> Self {
+        let half_size = bodies
+            .iter()
+            .map(|(_, position, _)| position.x.abs().max(position.y.abs()))
+            .fold(1.0_f32, f32::max);
+        let mut root = Node::new(Vec2::ZERO, half_size * 2.0);
+        for &(entity, position, mass) in bodies {
+            root.insert(entity, position, mass);
+        }
+        Self { root }
+    }
+
+    /// Approximates the gravitational force pulling `entity` at `position`
+    /// towards every other body in the tree, treating any node with
+    /// `width / distance < theta` as a single point mass at its center of mass.
+    pub fn force_on(&self, entity: Entity, position: Vec2, theta: f32, g: f32, epsilon: f32) -> Vec2 {
+        self.root.force_on(entity, position, theta, g, epsilon)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coincident_points_do_not_overflow_recursion() {
+        let bodies: Vec<(Entity, Vec2, f32)> = (0..8)
+            .map(|i| (Entity::from_raw(i), Vec2::ZERO, 1.0))
+            .collect();
+        let tree = Quadtree::build(&bodies);
+        // Every body shares one merged MAX_DEPTH leaf, so each one sees the
+        // whole leaf (itself included) excluded rather than a stack overflow.
+        for &(entity, position, _) in &bodies {
+            assert_eq!(tree.force_on(entity, position, 0.5, 1.0, 1.0), Vec2::ZERO);
+        }
+    }
+
+    #[test]
+    fn self_exclusion_only_zeroes_the_queried_entity_leaf() {
+        let a = Entity::from_raw(0);
+        let b = Entity::from_raw(1);
+        let bodies = [(a, Vec2::new(-50.0, 0.0), 1.0), (b, Vec2::new(50.0, 0.0), 1.0)];
+        let tree = Quadtree::build(&bodies);
+        let force = tree.force_on(a, Vec2::new(-50.0, 0.0), 0.5, 1.0, 0.0);
+        // b pulls a to the right; a's own leaf is excluded, b's is not.
+        assert!(force.x > 0.0);
+    }
+}
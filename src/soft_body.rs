@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+use crate::{make_constraint, make_point, ops, PhysicsRng};
+
+/// Builder namespace for spawning `Point`/`Constraint` meshes that hold
+/// together under `compute_constraints`, instead of wiring each one by hand.
+pub struct SoftBody;
+
+impl SoftBody {
+    /// A `cols` x `rows` grid of points, `spacing` apart, with structural
+    /// edges along rows/columns and shear diagonals within each cell.
+    pub fn grid(
+        commands: &mut Commands,
+        rng: &mut PhysicsRng,
+        cols: usize,
+        rows: usize,
+        spacing: f32,
+    ) -> Vec<Entity> {
+        let grid: Vec<Vec<Entity>> = (0..rows)
+            .map(|row| {
+                (0..cols)
+                    .map(|col| {
+                        let position = Vec2::new(col as f32 * spacing, row as f32 * spacing);
+                        commands.spawn(make_point(position, rng)).id()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for row in 0..rows {
+            for col in 0..cols {
+                if col + 1 < cols {
+                    commands.spawn(make_constraint(grid[row][col], grid[row][col + 1], spacing));
+                }
+                if row + 1 < rows {
+                    commands.spawn(make_constraint(grid[row][col], grid[row + 1][col], spacing));
+                }
+                if col + 1 < cols && row + 1 < rows {
+                    let diagonal = spacing * ops::sqrt(2.0);
+                    commands.spawn(make_constraint(grid[row][col], grid[row + 1][col + 1], diagonal));
+                    commands.spawn(make_constraint(grid[row][col + 1], grid[row + 1][col], diagonal));
+                }
+            }
+        }
+
+        grid.into_iter().flatten().collect()
+    }
+
+    /// A regular `n`-gon of `radius`, braced with spokes to a center point so
+    /// the loop resists collapsing under the perimeter constraints alone.
+    pub fn ring(commands: &mut Commands, rng: &mut PhysicsRng, n: usize, radius: f32) -> Vec<Entity> {
+        let points: Vec<Vec2> = (0..n)
+            .map(|i| {
+                let angle = std::f32::consts::TAU * i as f32 / n as f32;
+                Vec2::new(ops::cos(angle), ops::sin(angle)) * radius
+            })
+            .collect();
+        Self::from_outline(commands, rng, &points)
+    }
+
+    /// A closed outline, braced with a triangulated fan of spokes from the
+    /// centroid so the perimeter resists collapsing.
+    pub fn from_outline(commands: &mut Commands, rng: &mut PhysicsRng, points: &[Vec2]) -> Vec<Entity> {
+        let n = points.len();
+        if n == 0 {
+            return Vec::new();
+        }
+        let centroid = points.iter().copied().sum::<Vec2>() / n as f32;
+
+        let center = commands.spawn(make_point(centroid, rng)).id();
+        let mut entities = Vec::with_capacity(n + 1);
+        entities.push(center);
+        for &position in points {
+            entities.push(commands.spawn(make_point(position, rng)).id());
+        }
+
+        for i in 0..n {
+            let next = (i + 1) % n;
+            let edge_length = ops::distance(points[i], points[next]);
+            commands.spawn(make_constraint(entities[i + 1], entities[next + 1], edge_length));
+            let spoke_length = ops::distance(centroid, points[i]);
+            commands.spawn(make_constraint(center, entities[i + 1], spoke_length));
+        }
+
+        entities
+    }
+}
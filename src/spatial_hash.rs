@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+#[derive(Resource)]
+pub struct SpatialHash {
+    cell_size: f32,
+    buckets: HashMap<(i32, i32), Vec<Entity>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            buckets: HashMap::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2) {
+        self.buckets
+            .entry(self.cell_of(position))
+            .or_default()
+            .push(entity);
+    }
+
+    /// Returns every entity bucketed within the cells overlapping a square of
+    /// the given radius around `position`. Candidates outside the radius may
+    /// still be returned; callers are expected to do the precise distance check.
+    pub fn query_neighbors(&self, position: Vec2, radius: f32) -> Vec<Entity> {
+        let cell_radius = (radius / self.cell_size).ceil() as i32;
+        let (cx, cy) = self.cell_of(position);
+        let mut neighbors = Vec::new();
+        for dx in -cell_radius..=cell_radius {
+            for dy in -cell_radius..=cell_radius {
+                if let Some(bucket) = self.buckets.get(&(cx + dx, cy + dy)) {
+                    neighbors.extend_from_slice(bucket);
+                }
+            }
+        }
+        neighbors
+    }
+
+    fn cell_of(&self, position: Vec2) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coincident_points_share_a_bucket() {
+        let mut hash = SpatialHash::new(20.0);
+        let a = Entity::from_raw(0);
+        let b = Entity::from_raw(1);
+        hash.insert(a, Vec2::ZERO);
+        hash.insert(b, Vec2::ZERO);
+        let neighbors = hash.query_neighbors(Vec2::ZERO, 1.0);
+        assert_eq!(neighbors.len(), 2);
+        assert!(neighbors.contains(&a) && neighbors.contains(&b));
+    }
+
+    #[test]
+    fn query_radius_must_cover_the_full_pair_distance() {
+        let mut hash = SpatialHash::new(20.0);
+        let far = Entity::from_raw(1);
+        hash.insert(Entity::from_raw(0), Vec2::ZERO);
+        hash.insert(far, Vec2::new(40.0, 0.0));
+
+        // A query radius shorter than the separation can miss the bucket entirely.
+        assert!(!hash.query_neighbors(Vec2::ZERO, 10.0).contains(&far));
+        // Querying with the full pair distance (own_radius + partner_radius) finds it.
+        assert!(hash.query_neighbors(Vec2::ZERO, 40.0).contains(&far));
+    }
+}